@@ -6,6 +6,12 @@ pub struct SlotHandle {
     generation: usize,
 }
 
+impl SlotHandle {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
 struct Slot<T> {
     is_allocated: bool,
     generation: usize,
@@ -29,6 +35,10 @@ impl<T> Arena<T> {
         self.count
     }
 
+    pub(crate) fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
     pub fn add(&mut self, data: T) -> SlotHandle {
         let mut index = self.slots.len();
         for i in 0..self.slots.len() {
@@ -86,7 +96,10 @@ impl<T> Arena<T> {
 
     pub fn remove(&mut self, handle: SlotHandle) {
         let index = handle.index;
-        if index > self.slots.len() {
+        if index >= self.slots.len() {
+            return;
+        }
+        if self.slots[index].generation != handle.generation {
             return;
         }
         self.slots[index].is_allocated = false;
@@ -124,21 +137,17 @@ impl<'a, T> Iterator for AllocatedSlotIterator<'a, T> {
     type Item = SlotHandle;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.iter.next() {
-            Some(slot) => {
-                if slot.is_allocated {
-                    let handle = SlotHandle {
-                        index: self.index,
-                        generation: slot.generation,
-                    };
-                    self.index += 1;
-                    Some(handle)
-                } else {
-                    None
-                }
+        for slot in self.iter.by_ref() {
+            let index = self.index;
+            self.index += 1;
+            if slot.is_allocated {
+                return Some(SlotHandle {
+                    index,
+                    generation: slot.generation,
+                });
             }
-            None => None,
         }
+        None
     }
 }
 
@@ -170,4 +179,32 @@ mod tests {
         }
         assert!(iterated);
     }
+
+    #[test]
+    fn test_arena_iter_skips_a_freed_leading_slot() {
+        let mut arena: Arena<i32> = Arena::new();
+        let a = arena.add(1);
+        arena.add(2);
+        let c = arena.add(3);
+        arena.remove(a);
+        let values: Vec<i32> = arena.iter().map(|h| *arena.get(h).unwrap()).collect();
+        assert_eq!(values, vec![2, 3]);
+        assert!(arena.get(c).is_some());
+    }
+
+    #[test]
+    fn test_remove_is_a_no_op_on_a_stale_handle() {
+        let mut arena: Arena<i32> = Arena::new();
+        let a = arena.add(1);
+        arena.remove(a);
+        assert_eq!(arena.count(), 0);
+
+        // remove() bumped the slot's generation past `a`'s, so a second
+        // removal through the same (now-stale) handle must not match the
+        // slot again; without the generation check this double-decrements
+        // count and would later remove whatever node ends up reusing the
+        // slot, rather than rejecting the stale handle outright.
+        arena.remove(a);
+        assert_eq!(arena.count(), 0);
+    }
 }
@@ -0,0 +1,309 @@
+use std::cell::RefCell;
+use crate::{Digraph, NodeHandle};
+
+pub type DynCommand<T> = Box<dyn Command<T>>;
+
+pub trait Command<T> {
+    fn apply(&self, graph: &mut Digraph<T>);
+    fn undo(&self, graph: &Digraph<T>) -> DynCommand<T>;
+}
+
+pub struct AddNode<T> {
+    data: T,
+    handle: RefCell<Option<NodeHandle>>,
+}
+
+impl<T> AddNode<T> {
+    pub fn new(data: T) -> AddNode<T> {
+        AddNode {
+            data,
+            handle: RefCell::new(None),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Command<T> for AddNode<T> {
+    fn apply(&self, graph: &mut Digraph<T>) {
+        let handle = graph.add_node(self.data.clone());
+        *self.handle.borrow_mut() = Some(handle);
+    }
+
+    fn undo(&self, _graph: &Digraph<T>) -> DynCommand<T> {
+        let handle = self.handle.borrow().expect("AddNode must be applied before it can be undone");
+        Box::new(RemoveNode::new(handle))
+    }
+}
+
+pub struct AddEdge {
+    from: NodeHandle,
+    to: NodeHandle,
+}
+
+impl AddEdge {
+    pub fn new(from: NodeHandle, to: NodeHandle) -> AddEdge {
+        AddEdge { from, to }
+    }
+}
+
+impl<T: 'static> Command<T> for AddEdge {
+    fn apply(&self, graph: &mut Digraph<T>) {
+        graph.add_edge(self.from, self.to);
+    }
+
+    fn undo(&self, _graph: &Digraph<T>) -> DynCommand<T> {
+        Box::new(RemoveEdge::new(self.from, self.to))
+    }
+}
+
+// Not a request-level command on its own (callers only ever build
+// AddNode/AddEdge/RemoveNode directly); it exists purely as AddEdge's inverse.
+struct RemoveEdge {
+    from: NodeHandle,
+    to: NodeHandle,
+}
+
+impl RemoveEdge {
+    fn new(from: NodeHandle, to: NodeHandle) -> RemoveEdge {
+        RemoveEdge { from, to }
+    }
+}
+
+impl<T: 'static> Command<T> for RemoveEdge {
+    fn apply(&self, graph: &mut Digraph<T>) {
+        graph.remove_edge(self.from, self.to);
+    }
+
+    fn undo(&self, _graph: &Digraph<T>) -> DynCommand<T> {
+        Box::new(AddEdge::new(self.from, self.to))
+    }
+}
+
+pub struct RemoveNode<T> {
+    handle: NodeHandle,
+    // Captured at apply() time, before the node is actually removed, since
+    // that's the only moment its data and outgoing edges are still around to
+    // snapshot for undo.
+    removed: RefCell<Option<(T, Vec<NodeHandle>)>>,
+}
+
+impl<T> RemoveNode<T> {
+    pub fn new(handle: NodeHandle) -> RemoveNode<T> {
+        RemoveNode {
+            handle,
+            removed: RefCell::new(None),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Command<T> for RemoveNode<T> {
+    fn apply(&self, graph: &mut Digraph<T>) {
+        if let Some(node) = graph.node(self.handle) {
+            *self.removed.borrow_mut() = Some((node.data().clone(), node.edges().to_vec()));
+        }
+        graph.remove_node(self.handle);
+    }
+
+    fn undo(&self, _graph: &Digraph<T>) -> DynCommand<T> {
+        match self.removed.borrow().clone() {
+            Some((data, edges)) => Box::new(ReAddNode::new(data, edges)),
+            None => Box::new(NoOp::new()),
+        }
+    }
+}
+
+// RemoveNode's inverse: re-adds the node and its original outgoing edges.
+// Other nodes' edges that pointed at the removed handle are not rewritten.
+// If the removed node held the arena's last slot, Arena::remove dropped
+// that slot, so the restored node is re-created there at generation 0 and
+// gets the old handle back; those edges then resolve to it again. A reused
+// slot instead carries a bumped generation, so Arena::get rejects those
+// edges as stale and incoming edges are not restored.
+struct ReAddNode<T> {
+    data: T,
+    edges: Vec<NodeHandle>,
+    handle: RefCell<Option<NodeHandle>>,
+}
+
+impl<T> ReAddNode<T> {
+    fn new(data: T, edges: Vec<NodeHandle>) -> ReAddNode<T> {
+        ReAddNode {
+            data,
+            edges,
+            handle: RefCell::new(None),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Command<T> for ReAddNode<T> {
+    fn apply(&self, graph: &mut Digraph<T>) {
+        let handle = graph.add_node(self.data.clone());
+        for target in self.edges.iter() {
+            graph.add_edge(handle, *target);
+        }
+        *self.handle.borrow_mut() = Some(handle);
+    }
+
+    fn undo(&self, _graph: &Digraph<T>) -> DynCommand<T> {
+        let handle = self.handle.borrow().expect("ReAddNode must be applied before it can be undone");
+        Box::new(RemoveNode::new(handle))
+    }
+}
+
+struct NoOp;
+
+impl NoOp {
+    fn new() -> NoOp {
+        NoOp
+    }
+}
+
+impl<T: 'static> Command<T> for NoOp {
+    fn apply(&self, _graph: &mut Digraph<T>) {}
+
+    fn undo(&self, _graph: &Digraph<T>) -> DynCommand<T> {
+        Box::new(NoOp::new())
+    }
+}
+
+pub struct CommandHistory<T> {
+    entries: Vec<(DynCommand<T>, DynCommand<T>)>,
+    cursor: usize,
+}
+
+impl<T> CommandHistory<T> {
+    pub fn new() -> CommandHistory<T> {
+        CommandHistory {
+            entries: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    // Applies the forward command first so its inverse can be built from
+    // the resulting state (e.g. AddNode's inverse needs the handle that
+    // was just assigned), then records the pair and drops any redo tail.
+    pub fn push(&mut self, graph: &mut Digraph<T>, command: DynCommand<T>) {
+        command.apply(graph);
+        let inverse = command.undo(graph);
+        self.entries.truncate(self.cursor);
+        self.entries.push((command, inverse));
+        self.cursor += 1;
+    }
+
+    pub fn undo(&mut self, graph: &mut Digraph<T>) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        self.entries[self.cursor].1.apply(graph);
+        true
+    }
+
+    // Re-applies the forward command and, like push(), rebuilds its
+    // inverse from the resulting state. A redone AddNode/ReAddNode can be
+    // assigned a different handle, so the inverse stored the first time
+    // this entry was applied would otherwise go on pointing at the old one.
+    // Arena::remove is generation-checked, but a trailing slot it dropped
+    // is re-created at generation 0, so that old handle can match another
+    // node now holding the slot, and undoing through it would remove that
+    // node instead; hence recomputing the inverse here.
+    pub fn redo(&mut self, graph: &mut Digraph<T>) -> bool {
+        if self.cursor >= self.entries.len() {
+            return false;
+        }
+        self.entries[self.cursor].0.apply(graph);
+        let inverse = self.entries[self.cursor].0.undo(graph);
+        self.entries[self.cursor].1 = inverse;
+        self.cursor += 1;
+        true
+    }
+}
+
+impl<T> Default for CommandHistory<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_add_node() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let mut history = CommandHistory::new();
+        history.push(&mut graph, Box::new(AddNode::new(1)));
+        assert_eq!(graph.node_count(), 1);
+    }
+
+    #[test]
+    fn test_undo_add_node() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let mut history = CommandHistory::new();
+        history.push(&mut graph, Box::new(AddNode::new(1)));
+        history.undo(&mut graph);
+        assert_eq!(graph.node_count(), 0);
+    }
+
+    #[test]
+    fn test_redo_add_node() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let mut history = CommandHistory::new();
+        history.push(&mut graph, Box::new(AddNode::new(1)));
+        history.undo(&mut graph);
+        history.redo(&mut graph);
+        assert_eq!(graph.node_count(), 1);
+    }
+
+    #[test]
+    fn test_push_truncates_redo_tail() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let mut history = CommandHistory::new();
+        history.push(&mut graph, Box::new(AddNode::new(1)));
+        history.undo(&mut graph);
+        history.push(&mut graph, Box::new(AddNode::new(2)));
+        assert!(!history.redo(&mut graph));
+        assert_eq!(graph.node_count(), 1);
+    }
+
+    #[test]
+    fn test_redo_recomputes_inverse_instead_of_reusing_the_stale_one() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let mut history = CommandHistory::new();
+
+        history.push(&mut graph, Box::new(AddNode::new(1)));
+        history.push(&mut graph, Box::new(AddNode::new(2)));
+        history.undo(&mut graph);
+
+        // Something outside this history grows the graph in the meantime,
+        // so the redone AddNode below can't land back on its original slot.
+        let unrelated = graph.add_node(99);
+
+        history.redo(&mut graph);
+        assert_eq!(graph.node_count(), 3);
+
+        // If undo still used the inverse computed the first time this entry
+        // was applied, it would target the original (now stale) handle
+        // instead of the one just assigned above, and could remove the
+        // unrelated node instead of the one this redo actually created.
+        history.undo(&mut graph);
+        assert_eq!(graph.node_count(), 2);
+        assert!(graph.node(unrelated).is_some());
+    }
+
+    #[test]
+    fn test_undo_remove_node_restores_edges() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let mut history = CommandHistory::new();
+        history.push(&mut graph, Box::new(AddNode::new(1)));
+        history.push(&mut graph, Box::new(AddNode::new(2)));
+        let handles: Vec<NodeHandle> = graph.tsort().unwrap();
+        history.push(&mut graph, Box::new(AddEdge::new(handles[0], handles[1])));
+        history.push(&mut graph, Box::new(RemoveNode::new(handles[0])));
+        assert_eq!(graph.node_count(), 1);
+        history.undo(&mut graph);
+        assert_eq!(graph.node_count(), 2);
+        let restored = graph.tsort().unwrap().into_iter().find(|h| *h != handles[1]).unwrap();
+        assert_eq!(graph.node(restored).unwrap().edges().len(), 1);
+    }
+}
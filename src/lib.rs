@@ -1,7 +1,12 @@
 mod arena;
+mod command;
+mod reachability;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use arena::*;
+pub use command::*;
+pub use reachability::*;
 
 pub type NodeHandle = SlotHandle;
 
@@ -14,6 +19,10 @@ impl<T> Node<T> {
     pub fn data(&self) -> &T {
         &self.data
     }
+
+    pub fn edges(&self) -> &[NodeHandle] {
+        &self.edges
+    }
 }
 
 pub struct Digraph<T> {
@@ -53,6 +62,14 @@ impl<T> Digraph<T> {
         }
     }
 
+    pub(crate) fn remove_edge(&mut self, h1: NodeHandle, h2: NodeHandle) {
+        if let Some(node) = self.nodes.get_mut(h1) {
+            if let Some(pos) = node.edges.iter().position(|&h| h == h2) {
+                node.edges.remove(pos);
+            }
+        }
+    }
+
     pub fn tsort(&self) -> Result<Vec<NodeHandle>, TopologicalSortError> {
         let mut sorted = Vec::new();
         let mut seen = HashMap::new();
@@ -63,36 +80,357 @@ impl<T> Digraph<T> {
         Ok(sorted)
     }
 
-    fn tsort_internal(&self, h: NodeHandle, sorted: &mut Vec<NodeHandle>, seen: &mut HashMap<NodeHandle, SortStatus>) -> Result<(), TopologicalSortError> {
-        if let Some(node) = self.nodes.get(h) {
-            seen.entry(h).or_insert(SortStatus::Unseen);
-            match seen.get(&h).unwrap() {
-                SortStatus::Unseen => {
-                    seen.insert(h, SortStatus::Seen);
-                    for edge in node.edges.iter() {
-                        self.tsort_internal(*edge, sorted, seen)?;
+    // Iterative post-order DFS: an explicit work stack of (node, next edge
+    // index) stands in for the call stack so a long chain of nodes can't
+    // overflow it. A back edge to a node still `Seen` (on the stack) is a
+    // cycle; a node is pushed onto `sorted` once all of its edges are
+    // exhausted, so reversing the result at the end yields topological order.
+    fn tsort_internal(&self, start: NodeHandle, sorted: &mut Vec<NodeHandle>, seen: &mut HashMap<NodeHandle, SortStatus>) -> Result<(), TopologicalSortError> {
+        if self.nodes.get(start).is_none() {
+            return Err(TopologicalSortError::MissingNode);
+        }
+        seen.entry(start).or_insert(SortStatus::Unseen);
+        match seen.get(&start).unwrap() {
+            SortStatus::Seen => return Err(TopologicalSortError::Cycle(vec![start])),
+            SortStatus::Processed => return Ok(()),
+            SortStatus::Unseen => {},
+        }
+        seen.insert(start, SortStatus::Seen);
+
+        let mut stack: Vec<(NodeHandle, usize)> = vec![(start, 0)];
+        while let Some(&(h, edge_idx)) = stack.last() {
+            let next = match self.nodes.get(h) {
+                Some(node) => node.edges.get(edge_idx).copied(),
+                None => return Err(TopologicalSortError::MissingNode),
+            };
+            match next {
+                Some(next) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    seen.entry(next).or_insert(SortStatus::Unseen);
+                    match seen.get(&next).unwrap() {
+                        SortStatus::Unseen => {
+                            if self.nodes.get(next).is_none() {
+                                return Err(TopologicalSortError::MissingNode);
+                            }
+                            seen.insert(next, SortStatus::Seen);
+                            stack.push((next, 0));
+                        },
+                        SortStatus::Seen => {
+                            // `next` is still on the work stack, so the
+                            // suffix of `stack` from its position to the top
+                            // is the cycle: next -> ... -> h -> next.
+                            let pos = stack.iter().position(|&(node, _)| node == next).unwrap();
+                            let cycle = stack[pos..].iter().map(|&(node, _)| node).collect();
+                            return Err(TopologicalSortError::Cycle(cycle));
+                        },
+                        SortStatus::Processed => {
+                            // We've already done this node
+                        },
                     }
+                },
+                None => {
                     sorted.push(h);
+                    seen.insert(h, SortStatus::Processed);
+                    stack.pop();
                 },
-                SortStatus::Seen => {
-                    return Err(TopologicalSortError::Cycle);
+            }
+        }
+        Ok(())
+    }
+
+    // Builds the full transitive closure once so repeated `can_reach`
+    // queries don't each re-run a traversal, using Warshall's algorithm:
+    // seed each row with direct edges, then for every node `k`, anything
+    // that already reaches `k` also reaches everything `k` reaches. This
+    // needs no particular visiting order, so unlike a single reverse-
+    // topological pass it stays correct on cyclic graphs (a cycle just
+    // makes every node on it mutually reachable, rather than producing a
+    // truncated closure).
+    pub fn reachability(&self) -> Reachability {
+        let order: Vec<NodeHandle> = self.nodes.iter().collect();
+        let mut reachability = Reachability::new(self.nodes.capacity());
+
+        for &h in order.iter() {
+            reachability.record(h);
+            if let Some(node) = self.nodes.get(h) {
+                for &succ in node.edges.iter() {
+                    reachability.add_edge(h, succ);
+                }
+            }
+        }
+
+        for &k in order.iter() {
+            for &i in order.iter() {
+                if reachability.can_reach(i, k) {
+                    reachability.union(i, k);
+                }
+            }
+        }
+
+        reachability
+    }
+
+    // Partitions the matching nodes, in topological order (arena slot order
+    // when `tsort` fails, e.g. on a cycle), into maximal
+    // unbranching chains: a run is extended through a matching node only
+    // when it is the single matching successor of the current node *and*
+    // has exactly one incoming edge from a matching node, so joining two
+    // runs could never silently drop a branch or a merge.
+    pub fn collect_runs<F: Fn(&Node<T>) -> bool>(&self, filter: F) -> Vec<Vec<NodeHandle>> {
+        let order = self.tsort().unwrap_or_else(|_| self.nodes.iter().collect());
+
+        let mut in_degree: HashMap<NodeHandle, usize> = HashMap::new();
+        for h in self.nodes.iter() {
+            let node = match self.nodes.get(h) {
+                Some(node) => node,
+                None => continue,
+            };
+            if !filter(node) {
+                continue;
+            }
+            for &succ in node.edges.iter() {
+                *in_degree.entry(succ).or_insert(0) += 1;
+            }
+        }
+
+        let mut consumed: HashSet<NodeHandle> = HashSet::new();
+        let mut runs = Vec::new();
+        for &h in order.iter() {
+            let node = match self.nodes.get(h) {
+                Some(node) => node,
+                None => continue,
+            };
+            if consumed.contains(&h) || !filter(node) {
+                continue;
+            }
+
+            let mut run = vec![h];
+            consumed.insert(h);
+            let mut current = h;
+            while let Some(node) = self.nodes.get(current) {
+                let matching_successors: Vec<NodeHandle> = node.edges.iter()
+                    .filter(|&&succ| self.nodes.get(succ).is_some_and(&filter))
+                    .copied()
+                    .collect();
+                if matching_successors.len() != 1 {
+                    break;
+                }
+                let next = matching_successors[0];
+                if consumed.contains(&next) || *in_degree.get(&next).unwrap_or(&0) != 1 {
+                    break;
+                }
+                run.push(next);
+                consumed.insert(next);
+                current = next;
+            }
+            runs.push(run);
+        }
+        runs
+    }
+
+    fn degree_maps(&self) -> (HashMap<NodeHandle, usize>, HashMap<NodeHandle, usize>) {
+        let mut out_degree = HashMap::new();
+        let mut in_degree = HashMap::new();
+        for h in self.nodes.iter() {
+            out_degree.entry(h).or_insert(0);
+            in_degree.entry(h).or_insert(0);
+            if let Some(node) = self.nodes.get(h) {
+                *out_degree.get_mut(&h).unwrap() += node.edges.len();
+                for &succ in node.edges.iter() {
+                    *in_degree.entry(succ).or_insert(0) += 1;
+                }
+            }
+        }
+        (out_degree, in_degree)
+    }
+
+    // Weak connectivity (edges treated as undirected) among the given
+    // nodes, via a plain reachability flood from the first one.
+    fn is_weakly_connected(&self, nodes: &[NodeHandle]) -> bool {
+        if nodes.is_empty() {
+            return true;
+        }
+        let mut adjacency: HashMap<NodeHandle, Vec<NodeHandle>> = HashMap::new();
+        for h in self.nodes.iter() {
+            if let Some(node) = self.nodes.get(h) {
+                for &succ in node.edges.iter() {
+                    adjacency.entry(h).or_default().push(succ);
+                    adjacency.entry(succ).or_default().push(h);
+                }
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut stack = vec![nodes[0]];
+        visited.insert(nodes[0]);
+        while let Some(h) = stack.pop() {
+            if let Some(neighbors) = adjacency.get(&h) {
+                for &n in neighbors.iter() {
+                    if visited.insert(n) {
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+
+        nodes.iter().all(|h| visited.contains(h))
+    }
+
+    // A directed graph has an Eulerian circuit iff every vertex with
+    // nonzero degree is balanced (in == out) and those vertices form one
+    // connected component; it has an Eulerian path iff exactly one vertex
+    // is unbalanced by +1 (the start) and exactly one by -1 (the end),
+    // with the rest balanced, plus the same connectivity requirement.
+    pub fn eulerian_kind(&self) -> EulerianKind {
+        let (out_degree, in_degree) = self.degree_maps();
+        let active: Vec<NodeHandle> = self.nodes.iter()
+            .filter(|h| *out_degree.get(h).unwrap_or(&0) + *in_degree.get(h).unwrap_or(&0) > 0)
+            .collect();
+
+        if !self.is_weakly_connected(&active) {
+            return EulerianKind::None;
+        }
+
+        let mut starts = 0;
+        let mut ends = 0;
+        for h in active.iter() {
+            let out = *out_degree.get(h).unwrap_or(&0) as i64;
+            let inn = *in_degree.get(h).unwrap_or(&0) as i64;
+            match out - inn {
+                0 => {},
+                1 => starts += 1,
+                -1 => ends += 1,
+                _ => return EulerianKind::None,
+            }
+        }
+
+        if starts == 0 && ends == 0 {
+            EulerianKind::Circuit
+        } else if starts == 1 && ends == 1 {
+            EulerianKind::Path
+        } else {
+            EulerianKind::None
+        }
+    }
+
+    // Hierholzer's algorithm: keep a mutable per-node cursor into its edge
+    // list, push the start, repeatedly follow and consume an unused
+    // outgoing edge while one exists, and when a node has none left pop it
+    // onto the trail. Reversing the popped order yields the Eulerian trail.
+    pub fn eulerian_path(&self) -> Option<Vec<NodeHandle>> {
+        let kind = self.eulerian_kind();
+        if kind == EulerianKind::None {
+            return None;
+        }
+
+        let (out_degree, in_degree) = self.degree_maps();
+        let total_edges: usize = out_degree.values().sum();
+        if total_edges == 0 {
+            return Some(Vec::new());
+        }
+
+        let start = self.nodes.iter().find(|h| {
+            let out = *out_degree.get(h).unwrap_or(&0);
+            if out == 0 {
+                return false;
+            }
+            if kind == EulerianKind::Path {
+                out > *in_degree.get(h).unwrap_or(&0)
+            } else {
+                true
+            }
+        })?;
+
+        let mut cursor: HashMap<NodeHandle, usize> = HashMap::new();
+        let mut stack = vec![start];
+        let mut trail = Vec::new();
+        while let Some(&v) = stack.last() {
+            let idx = *cursor.entry(v).or_insert(0);
+            let next_edge = self.nodes.get(v).and_then(|n| n.edges.get(idx).copied());
+            match next_edge {
+                Some(next) => {
+                    cursor.insert(v, idx + 1);
+                    stack.push(next);
                 },
-                SortStatus::Processed => {
-                    // We've already done this node
+                None => {
+                    trail.push(v);
+                    stack.pop();
                 },
             }
-            seen.insert(h, SortStatus::Processed);
-            Ok(())
-        } else {
-            Err(TopologicalSortError::MissingNode)
         }
+        trail.reverse();
+
+        if trail.len() != total_edges + 1 {
+            return None;
+        }
+
+        Some(trail)
     }
+
+    // Same post-order DFS as `tsort`, but a back edge into a node still
+    // `Seen` is recorded as a cycle and skipped rather than aborting the
+    // whole sort, so build/link-style callers always get an ordering to
+    // work with even over cyclic input.
+    pub fn tsort_lenient(&self) -> (Vec<NodeHandle>, Vec<Vec<NodeHandle>>) {
+        let mut sorted = Vec::new();
+        let mut cycles = Vec::new();
+        let mut seen: HashMap<NodeHandle, SortStatus> = HashMap::new();
+        for h in self.nodes.iter() {
+            self.tsort_lenient_internal(h, &mut sorted, &mut cycles, &mut seen);
+        }
+        sorted.reverse();
+        (sorted, cycles)
+    }
+
+    fn tsort_lenient_internal(&self, start: NodeHandle, sorted: &mut Vec<NodeHandle>, cycles: &mut Vec<Vec<NodeHandle>>, seen: &mut HashMap<NodeHandle, SortStatus>) {
+        seen.entry(start).or_insert(SortStatus::Unseen);
+        match seen.get(&start).unwrap() {
+            SortStatus::Seen | SortStatus::Processed => return,
+            SortStatus::Unseen => {},
+        }
+        seen.insert(start, SortStatus::Seen);
+
+        let mut stack: Vec<(NodeHandle, usize)> = vec![(start, 0)];
+        while let Some(&(h, edge_idx)) = stack.last() {
+            let next = self.nodes.get(h).and_then(|node| node.edges.get(edge_idx).copied());
+            match next {
+                Some(next) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    seen.entry(next).or_insert(SortStatus::Unseen);
+                    match seen.get(&next).unwrap() {
+                        SortStatus::Unseen => {
+                            if self.nodes.get(next).is_some() {
+                                seen.insert(next, SortStatus::Seen);
+                                stack.push((next, 0));
+                            }
+                        },
+                        SortStatus::Seen => {
+                            cycles.push(vec![h, next]);
+                        },
+                        SortStatus::Processed => {},
+                    }
+                },
+                None => {
+                    sorted.push(h);
+                    seen.insert(h, SortStatus::Processed);
+                    stack.pop();
+                },
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EulerianKind {
+    Circuit,
+    Path,
+    None,
 }
 
 #[derive(Debug)]
 pub enum TopologicalSortError {
     MissingNode,
-    Cycle,
+    Cycle(Vec<NodeHandle>),
 }
 
 enum SortStatus {
@@ -276,6 +614,171 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_collect_runs_single_chain() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        let runs = graph.collect_runs(|_| true);
+        assert_eq!(runs, vec![vec![h1, h2, h3]]);
+    }
+
+    #[test]
+    fn test_collect_runs_breaks_at_merge_point() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        let h4 = graph.add_node(4);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        graph.add_edge(h4, h2);
+        let runs = graph.collect_runs(|_| true);
+        assert_eq!(runs, vec![vec![h4], vec![h1], vec![h2, h3]]);
+    }
+
+    #[test]
+    fn test_collect_runs_ignores_non_matching_nodes() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        let runs = graph.collect_runs(|n| *n.data() != 2);
+        assert_eq!(runs, vec![vec![h1], vec![h3]]);
+    }
+
+    #[test]
+    fn test_collect_runs_joins_through_merge_from_non_matching_predecessor() {
+        let mut graph = Digraph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        let c = graph.add_node(3);
+        graph.add_edge(a, c);
+        graph.add_edge(b, c);
+        let runs = graph.collect_runs(|n| *n.data() != 2);
+        assert_eq!(runs, vec![vec![a, c]]);
+    }
+
+    #[test]
+    fn test_eulerian_kind_circuit() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        graph.add_edge(h3, h1);
+        assert_eq!(graph.eulerian_kind(), EulerianKind::Circuit);
+    }
+
+    #[test]
+    fn test_eulerian_kind_path() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        assert_eq!(graph.eulerian_kind(), EulerianKind::Path);
+    }
+
+    #[test]
+    fn test_eulerian_kind_none_when_disconnected() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        let h4 = graph.add_node(4);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h3, h4);
+        assert_eq!(graph.eulerian_kind(), EulerianKind::None);
+    }
+
+    #[test]
+    fn test_eulerian_kind_none_when_unbalanced() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h1, h3);
+        assert_eq!(graph.eulerian_kind(), EulerianKind::None);
+    }
+
+    #[test]
+    fn test_eulerian_path_circuit_visits_every_edge() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        graph.add_edge(h3, h1);
+        let trail = graph.eulerian_path().expect("circuit should have a trail");
+        assert_eq!(trail.len(), 4);
+        assert_eq!(trail.first(), trail.last());
+        for pair in trail.windows(2) {
+            assert!(graph.node(pair[0]).unwrap().edges().contains(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_eulerian_path_simple_chain() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        assert_eq!(graph.eulerian_path(), Some(vec![h1, h2, h3]));
+    }
+
+    #[test]
+    fn test_eulerian_path_none_when_disconnected() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        let h4 = graph.add_node(4);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h3, h4);
+        assert_eq!(graph.eulerian_path(), None);
+    }
+
+    #[test]
+    fn test_tsort_lenient_acyclic_matches_tsort() {
+        let mut graph = Digraph::new();
+        let h2 = graph.add_node(2);
+        let h1 = graph.add_node(1);
+        graph.add_edge(h1, h2);
+        let h3 = graph.add_node(3);
+        let h4 = graph.add_node(4);
+        graph.add_edge(h2, h3);
+        graph.add_edge(h1, h4);
+        let (sorted, cycles) = graph.tsort_lenient();
+        assert_eq!(sorted, graph.tsort().unwrap());
+        assert!(cycles.is_empty());
+    }
+
+    #[test]
+    fn test_tsort_lenient_breaks_cycle_and_still_orders_every_node() {
+        let mut graph = Digraph::new();
+        let h1 = graph.add_node(1);
+        let h2 = graph.add_node(2);
+        let h3 = graph.add_node(3);
+        graph.add_edge(h1, h2);
+        graph.add_edge(h2, h3);
+        graph.add_edge(h3, h1);
+        let (sorted, cycles) = graph.tsort_lenient();
+        assert_eq!(sorted.len(), graph.node_count());
+        assert_eq!(cycles, vec![vec![h3, h1]]);
+    }
+
     mod parser_tests {
         use super::*;
 
@@ -6,11 +6,12 @@ use std::fs::File;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let lenient = args.iter().any(|arg| arg == "--lenient");
+    let file_name = args.iter().skip(1).find(|arg| *arg != "--lenient");
 
     let stdin = io::stdin();
-    
-    let mut input: Box<dyn io::BufRead> = if args.len() > 1 {
-        let file_name = &args[1];
+
+    let mut input: Box<dyn io::BufRead> = if let Some(file_name) = file_name {
         let file = match File::open(file_name) {
             Ok(file) => file,
             Err(err) => panic!("Could not open file {}: {}", file_name, err),
@@ -38,6 +39,22 @@ fn main() {
         }
     }
     let graph = parser.graph();
+
+    if lenient {
+        let (sorted, cycles) = graph.tsort_lenient();
+        for handle in sorted.iter() {
+            let node = graph.node(*handle).unwrap();
+            println!("{}", node.data());
+        }
+        for cycle in cycles.iter() {
+            let names: Vec<&String> = cycle.iter()
+                .map(|handle| graph.node(*handle).unwrap().data())
+                .collect();
+            eprintln!("Warning: cycle broken at {:?}", names);
+        }
+        return;
+    }
+
     match graph.tsort() {
         Ok(sorted) => {
             for handle in sorted.iter() {
@@ -47,8 +64,11 @@ fn main() {
         },
         Err(err) => {
             match err {
-                TopologicalSortError::Cycle => {
-                    println!("Cannot sort, graph contains a cycle");
+                TopologicalSortError::Cycle(nodes) => {
+                    let names: Vec<&String> = nodes.iter()
+                        .map(|handle| graph.node(*handle).unwrap().data())
+                        .collect();
+                    println!("Cannot sort, graph contains a cycle: {:?}", names);
                 },
                 _ => {
                     panic!("{:?}", err);
@@ -0,0 +1,184 @@
+use crate::NodeHandle;
+
+// A row per node, each packed into words of u64s; bit j of row i means
+// "node i reaches node j". Rows/columns are addressed by the arena's raw
+// slot index, not by a compacted node count, so they line up directly with
+// NodeHandle::index() without needing a separate remapping table.
+struct BitMatrix {
+    rows: Vec<Vec<u64>>,
+}
+
+impl BitMatrix {
+    fn new(size: usize) -> BitMatrix {
+        let words_per_row = size.div_ceil(64);
+        BitMatrix {
+            rows: vec![vec![0u64; words_per_row]; size],
+        }
+    }
+
+    fn set(&mut self, row: usize, col: usize) {
+        self.rows[row][col / 64] |= 1 << (col % 64);
+    }
+
+    fn get(&self, row: usize, col: usize) -> bool {
+        (self.rows[row][col / 64] >> (col % 64)) & 1 != 0
+    }
+
+    // Word-wise union of `src`'s row into `dst`'s row, the same merge a
+    // bitvector union does.
+    fn union_row(&mut self, dst: usize, src: usize) {
+        if dst == src {
+            return;
+        }
+        let src_words = self.rows[src].clone();
+        for (d, s) in self.rows[dst].iter_mut().zip(src_words.iter()) {
+            *d |= s;
+        }
+    }
+}
+
+pub struct Reachability {
+    matrix: BitMatrix,
+    handles: Vec<Option<NodeHandle>>,
+}
+
+impl Reachability {
+    pub(crate) fn new(capacity: usize) -> Reachability {
+        Reachability {
+            matrix: BitMatrix::new(capacity),
+            handles: vec![None; capacity],
+        }
+    }
+
+    pub(crate) fn record(&mut self, handle: NodeHandle) {
+        self.handles[handle.index()] = Some(handle);
+    }
+
+    pub(crate) fn add_edge(&mut self, from: NodeHandle, to: NodeHandle) {
+        self.matrix.set(from.index(), to.index());
+    }
+
+    pub(crate) fn union(&mut self, dst: NodeHandle, src: NodeHandle) {
+        self.matrix.union_row(dst.index(), src.index());
+    }
+
+    pub fn can_reach(&self, a: NodeHandle, b: NodeHandle) -> bool {
+        self.matrix.get(a.index(), b.index())
+    }
+
+    pub fn reachable_from(&self, a: NodeHandle) -> ReachableIter<'_> {
+        ReachableIter {
+            reachability: self,
+            from: a.index(),
+            col: 0,
+        }
+    }
+}
+
+pub struct ReachableIter<'a> {
+    reachability: &'a Reachability,
+    from: usize,
+    col: usize,
+}
+
+impl<'a> Iterator for ReachableIter<'a> {
+    type Item = NodeHandle;
+
+    fn next(&mut self) -> Option<NodeHandle> {
+        while self.col < self.reachability.handles.len() {
+            let col = self.col;
+            self.col += 1;
+            if self.reachability.matrix.get(self.from, col) {
+                if let Some(handle) = self.reachability.handles[col] {
+                    return Some(handle);
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Digraph;
+
+    #[test]
+    fn test_can_reach_direct_and_transitive() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        let c = graph.add_node(3);
+        graph.add_edge(a, b);
+        graph.add_edge(b, c);
+
+        let reach = graph.reachability();
+        assert!(reach.can_reach(a, b));
+        assert!(reach.can_reach(a, c));
+        assert!(!reach.can_reach(c, a));
+    }
+
+    #[test]
+    fn test_can_reach_is_false_with_no_path() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+
+        let reach = graph.reachability();
+        assert!(!reach.can_reach(a, b));
+    }
+
+    #[test]
+    fn test_can_reach_is_correct_around_a_cycle() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        let c = graph.add_node(3);
+        graph.add_edge(a, b);
+        graph.add_edge(b, c);
+        graph.add_edge(c, a);
+
+        let reach = graph.reachability();
+        assert!(reach.can_reach(c, b));
+        assert!(reach.can_reach(a, c));
+        assert!(reach.can_reach(b, a));
+    }
+
+    #[test]
+    fn test_reachable_from_collects_all_descendants() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        let c = graph.add_node(3);
+        let d = graph.add_node(4);
+        graph.add_edge(a, b);
+        graph.add_edge(a, c);
+        graph.add_edge(c, d);
+
+        let reach = graph.reachability();
+        let mut descendants: Vec<i32> = reach.reachable_from(a)
+            .map(|h| *graph.node(h).unwrap().data())
+            .collect();
+        descendants.sort();
+        assert_eq!(descendants, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_reachability_is_correct_past_a_removed_node() {
+        let mut graph: Digraph<i32> = Digraph::new();
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        let c = graph.add_node(3);
+        let d = graph.add_node(4);
+        graph.remove_node(b);
+        graph.add_edge(a, c);
+        graph.add_edge(c, d);
+
+        let reach = graph.reachability();
+        assert!(reach.can_reach(a, d));
+        let mut descendants: Vec<i32> = reach.reachable_from(a)
+            .map(|h| *graph.node(h).unwrap().data())
+            .collect();
+        descendants.sort();
+        assert_eq!(descendants, vec![3, 4]);
+    }
+}